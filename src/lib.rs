@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque}, fmt::Write, str::{Chars, FromStr}
+    collections::{HashMap, HashSet, VecDeque}, fmt::Write, marker::PhantomData, str::{Chars, FromStr}
 };
 
 use anyhow::{Context, Result};
@@ -30,14 +30,82 @@ enum ArgMode {
     Unset,
 }
 
+/// Consumes a (possibly quoted/escaped) value token, starting from `next_c`,
+/// and appends it to `value_buf`. Shared by the normal flag-value/positional
+/// loop and by the inline `--flag=value`/`-fVALUE` handling in the name loop,
+/// so both stay in sync on quoting and escaping rules.
+fn collect_value(chars: &mut Chars, mut next_c: Option<char>, value_buf: &mut String) -> Result<Option<char>> {
+    let mut quote = None;
+    loop {
+        match next_c {
+            Some('\\') => match chars.next() {
+                Some(c) => value_buf.write_char(c)?,
+                None => anyhow::bail!("EOL after escape sequence"),
+            },
+            // Opening quotes.
+            Some('"' | '\'') if quote.is_none() => quote = next_c,
+            // Closing quotes.
+            Some('"' | '\'') if quote == next_c => break,
+            // Whitespace when not in quotes.
+            Some(_) if next_c.unwrap().is_whitespace() && quote.is_none() => break,
+            // Any remaining character, includes "other" quotes when quoted.
+            Some(_) => value_buf.write_char(next_c.unwrap())?,
+            None if quote.is_some() => anyhow::bail!("EOL before closing quote"),
+            None => break,
+        };
+
+        next_c = chars.next();
+    }
+
+    Ok(next_c)
+}
+
+/// Shared "required flag was not provided" message, used by `Flag`,
+/// `RepeatedFlag` and `CommandSpec::parse` so the wording can't drift out of
+/// sync between the three places a missing required flag is reported.
+fn missing_flag_message(name: &str, alias: Option<&str>) -> String {
+    match alias {
+        Some(alias) => format!("required flag '--{}' ('-{}') was not provided", name, alias),
+        None => format!("required flag '--{}' was not provided", name),
+    }
+}
+
+/// A single `dispatch` route: a verb name paired with the handler it maps
+/// to. Aliased so `dispatch`'s signature doesn't spell out the function
+/// pointer type inline (which trips clippy's `type_complexity` lint).
+pub type Route<'a> = (&'a str, fn(&mut Command) -> Result<()>);
+
 #[derive(Debug, Clone)]
 pub struct Command {
     pub arguments: VecDeque<String>,
     pub switches: HashSet<String>,
     pub flags: HashMap<String, VecDeque<String>>,
+    // Raw text before and after the first positional argument (the "verb"),
+    // kept around so subcommand-aware callers can re-parse each side as its
+    // own `Command` without having to guess at token boundaries themselves.
+    subcommand_prefix: Option<String>,
+    subcommand_suffix: Option<String>,
 }
 
 impl Command {
+    /// Parses `command` into switches, flags and positional arguments.
+    ///
+    /// ## Switches before the subcommand verb
+    ///
+    /// This parser has no schema, so a flag/switch name followed by a bare
+    /// word is ambiguous: `--verbose commit` cannot be told apart from a
+    /// flag `--verbose` whose value is `commit`. It's resolved the same way
+    /// everywhere else in this parser — the bare word is consumed as the
+    /// value — which means a global switch written before the verb will eat
+    /// the verb itself unless it's immediately followed by another
+    /// `-`-prefixed token (`--verbose --path x commit -m msg`) or written
+    /// with `=` (`--verbose=true commit`). The verb is fixed here, at parse
+    /// time: consuming flags afterwards does not change it. In that failure
+    /// case, `subcommand`/`split_at_subcommand` take the next bare positional
+    /// that is not a flag value as the verb, or, if there is none, return
+    /// `None` (surfaced by `dispatch` as "Missing subcommand").
+    /// `CommandSpec::validate` only inspects the already-parsed `Command`,
+    /// so it does not resolve this ambiguity either.
     pub fn parse(command: String) -> Result<Self> {
         let mut name_buf = String::new();
         let mut value_buf = String::new();
@@ -47,6 +115,10 @@ impl Command {
         let mut flags = HashMap::new();
         let mut arg_mode = ArgMode::Unset;
 
+        let mut verb_start = None;
+        let mut subcommand_prefix = None;
+        let mut subcommand_suffix = None;
+
         let mut chars = command.chars();
         let mut next_c = chars.next_non_whitespace();
 
@@ -76,6 +148,18 @@ impl Command {
                         match next_c {
                             Some('\\') => anyhow::bail!("Escapes are not valid in names."),
                             Some('"' | '\'') => anyhow::bail!("Quotes are not valid in names."),
+                            // `--name=value` / `-abc=value`: the name ends at the `=`,
+                            // and the rest of the token is parsed as its value.
+                            Some('=') => {
+                                arg_mode = match arg_mode {
+                                    ArgMode::FlagName => ArgMode::FlagValue,
+                                    ArgMode::ShortFlagName => ArgMode::ShortFlagValue,
+                                    _ => unreachable!(),
+                                };
+                                next_c = chars.next();
+                                collect_value(&mut chars, next_c, &mut value_buf)?;
+                                break;
+                            }
                             Some('-') if arg_mode == ArgMode::ShortFlagName => {
                                 anyhow::bail!("Short flags cannot contain hyphens")
                             }
@@ -83,6 +167,17 @@ impl Command {
                                 anyhow::bail!("Flag names cannot start with a hyphen")
                             }
                             Some(c) if c.is_whitespace() => break,
+                            // `-i12`: a digit directly following the flag letter (with no
+                            // `=`) starts an inline value, same as the trailing letter of
+                            // an `-abcd 1` cluster taking the space-separated value.
+                            Some(c) if arg_mode == ArgMode::ShortFlagName
+                                && !name_buf.is_empty()
+                                && c.is_ascii_digit() =>
+                            {
+                                arg_mode = ArgMode::ShortFlagValue;
+                                collect_value(&mut chars, next_c, &mut value_buf)?;
+                                break;
+                            }
                             Some(c) => name_buf.write_char(c)?,
                             None if name_buf.is_empty() => anyhow::bail!("EOL before flag name."),
                             None => break,
@@ -93,6 +188,17 @@ impl Command {
                 }
                 // Flag value or positional argument.
                 _ => {
+                    // The first bare positional is the subcommand "verb". Remember where
+                    // its token starts so the text after it can be re-parsed as a child
+                    // `Command` once the verb itself is known.
+                    if arg_mode == ArgMode::Unset && arguments.is_empty() && verb_start.is_none() {
+                        verb_start = Some(
+                            command.len()
+                                - chars.as_str().len()
+                                - next_c.map_or(0, |c| c.len_utf8()),
+                        );
+                    }
+
                     arg_mode = match arg_mode {
                         ArgMode::FlagName => ArgMode::FlagValue,
                         ArgMode::ShortFlagName => ArgMode::ShortFlagValue,
@@ -101,26 +207,17 @@ impl Command {
                         _ => unreachable!(),
                     };
 
-                    let mut quote = None;
-                    loop {
-                        match next_c {
-                            Some('\\') => match chars.next() {
-                                Some(c) => value_buf.write_char(c)?,
-                                None => anyhow::bail!("EOL after escape sequence"),
-                            },
-                            // Opening quotes.
-                            Some('"' | '\'') if quote.is_none() => quote = next_c,
-                            // Closing quotes.
-                            Some('"' | '\'') if quote == next_c => break,
-                            // Whitespace when not in quotes.
-                            Some(_) if next_c.unwrap().is_whitespace() && quote.is_none() => break,
-                            // Any remaining character, includes "other" quotes when quoted.
-                            Some(_) => value_buf.write_char(next_c.unwrap())?,
-                            None if quote.is_some() => anyhow::bail!("EOL before closing quote"),
-                            None => break,
-                        };
+                    next_c = collect_value(&mut chars, next_c, &mut value_buf)?;
 
-                        next_c = chars.next();
+                    if let (Some(start), None) = (verb_start, &subcommand_suffix) {
+                        subcommand_prefix = Some(command[..start].to_string());
+                        subcommand_suffix = Some(match next_c {
+                            // A quoted verb stops on its closing quote, which
+                            // belongs to the verb, not to the text after it.
+                            Some('"' | '\'') => chars.as_str().to_string(),
+                            Some(c) => format!("{}{}", c, chars.as_str()),
+                            None => String::new(),
+                        });
                     }
                 }
             }
@@ -164,10 +261,11 @@ impl Command {
                     name_buf.clear();
                     arg_mode = ArgMode::Unset;
                 }
-                // A flag with both a name and a value -> flag.
+                // A flag with both a name and a value -> flag. The value may
+                // legitimately be empty (`--k=`, `--k=""`): an inline `=`
+                // unconditionally commits to a value, even a zero-length one.
                 ArgMode::FlagValue => {
                     debug_assert!(!name_buf.is_empty(), "flag with empty name_buf");
-                    debug_assert!(!value_buf.is_empty(), "flag with empty value_buf");
                     flags
                         .entry(name_buf.clone())
                         .or_insert_with(|| VecDeque::new())
@@ -177,9 +275,10 @@ impl Command {
                     value_buf.clear();
                     arg_mode = ArgMode::Unset;
                 }
+                // Same as `FlagValue`: an inline `=`/digit can commit to an
+                // empty value (`-abc=`), which is legitimate, not a bug.
                 ArgMode::ShortFlagValue => {
                     debug_assert!(!name_buf.is_empty(), "flag with empty name_buf");
-                    debug_assert!(!value_buf.is_empty(), "flag with empty value_buf");
 
                     // Short flags can be of the form "-abcd 1":
                     // "d" becomes a flag with value 1, the rest become switches.
@@ -202,9 +301,56 @@ impl Command {
             arguments,
             switches,
             flags,
+            subcommand_prefix,
+            subcommand_suffix,
         })
     }
 
+    /// Splits this command at its first positional argument (the "verb"),
+    /// returning a fresh parent `Command` built only from the flags/switches
+    /// that preceded it, plus the raw text that followed for the caller to
+    /// parse lazily (e.g. only once a route is actually matched).
+    pub fn split_at_subcommand(self) -> Option<(Command, String)> {
+        let parent = Command::parse(self.subcommand_prefix?).ok()?;
+        Some((parent, self.subcommand_suffix?))
+    }
+
+    /// Pops the leading positional argument as a subcommand verb and eagerly
+    /// re-parses everything after it into a child `Command`, leaving only
+    /// the flags/switches/arguments that preceded the verb on `self`.
+    ///
+    /// Like `split_at_subcommand`, this re-parses the prefix from scratch
+    /// rather than reusing `self` as-is: `self` was produced by a single
+    /// parse over the *whole* input, so its `flags`/`switches`/`arguments`
+    /// also contain everything that came after the verb. Overwriting `self`
+    /// with a fresh parse of just the prefix keeps the parent free of child
+    /// data, the same guarantee `split_at_subcommand` already gives.
+    pub fn subcommand(&mut self) -> Option<(String, Command)> {
+        let prefix = self.subcommand_prefix.take()?;
+        let suffix = self.subcommand_suffix.take()?;
+        let verb = self.arguments.pop_front()?;
+
+        let parent = Command::parse(prefix).ok()?;
+        let child = Command::parse(suffix).ok()?;
+
+        *self = parent;
+
+        Some((verb, child))
+    }
+
+    /// Dispatches to the handler whose route name matches the leading verb,
+    /// passing it a `Command` containing only the tokens after that verb.
+    pub fn dispatch(&mut self, routes: &[Route]) -> Result<()> {
+        let (verb, mut child) = self.subcommand().context("Missing subcommand")?;
+
+        let (_, handler) = routes
+            .iter()
+            .find(|(name, _)| *name == verb)
+            .with_context(|| format!("Unknown subcommand '{}'", verb))?;
+
+        handler(&mut child)
+    }
+
     pub fn drain_arguments(&mut self) -> VecDeque<String> {
         let mut out = VecDeque::new();
         out.extend(self.arguments.drain(..));
@@ -258,6 +404,7 @@ where
     name: &'a str,
     alias: Option<&'a str>,
     default: Option<T>,
+    required: bool,
 }
 
 impl<'a, T: FromStr> Flag<'a, T>
@@ -269,6 +416,7 @@ where
             name,
             alias: None,
             default: None,
+            required: false,
         }
     }
 
@@ -282,10 +430,34 @@ where
         self
     }
 
+    /// Marks this flag as required: if it's absent and has no default,
+    /// `parse` fails with a dedicated "required flag ... was not provided"
+    /// error instead of the generic missing-value error.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Switches this flag from single-valued to repeatable, returning a
+    /// `RepeatedFlag` whose `parse_all` collects every occurrence into a
+    /// `Vec<T>` instead of keeping only the first.
+    pub fn repeated(self) -> RepeatedFlag<'a, T> {
+        RepeatedFlag {
+            name: self.name,
+            alias: self.alias,
+            required: self.required,
+            _value: PhantomData,
+        }
+    }
+
+    fn missing_error(&self) -> anyhow::Error {
+        anyhow::anyhow!(missing_flag_message(self.name, self.alias))
+    }
+
     pub fn parse(self, cmd: &mut Command) -> Result<T> {
         let res = match cmd.get_next_flag(self.name) {
             Ok(v) => Ok(v),
-            Err(v) if self.alias.is_none() => return Err(v),
+            Err(v) if self.alias.is_none() => Err(v),
             _ => {
                 let alias = self.alias.unwrap();
                 cmd.get_next_flag(alias).context(format!(
@@ -297,7 +469,823 @@ where
 
         match res {
             Ok(v) => v.parse::<T>().context(format!("Couldn't parse flag.")),
-            Err(e) => self.default.map_or(Err(e), |v| Ok(v)),
+            Err(e) => match self.default {
+                Some(v) => Ok(v),
+                None if self.required => Err(self.missing_error()),
+                None => Err(e),
+            },
+        }
+    }
+
+}
+
+/// A `Flag` that has been switched to repeatable via `Flag::repeated`. Values
+/// are kept in `Command::flags` as a `VecDeque` per name already, so this
+/// just needs to drain all of them instead of popping the first.
+pub struct RepeatedFlag<'a, T: FromStr>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    name: &'a str,
+    alias: Option<&'a str>,
+    required: bool,
+    _value: PhantomData<T>,
+}
+
+impl<'a, T: FromStr> RepeatedFlag<'a, T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    fn missing_error(&self) -> anyhow::Error {
+        anyhow::anyhow!(missing_flag_message(self.name, self.alias))
+    }
+
+    /// Drains every occurrence of this flag (under both its name and alias)
+    /// and parses each one, collecting every parse failure into a single
+    /// aggregated error tagged with the offending element's index.
+    pub fn parse_all(self, cmd: &mut Command) -> Result<Vec<T>> {
+        let mut values = cmd.drain_flag(self.name).unwrap_or_default();
+        if let Some(alias_values) = self.alias.and_then(|alias| cmd.drain_flag(alias)) {
+            values.extend(alias_values);
+        }
+
+        if values.is_empty() {
+            return if self.required {
+                Err(self.missing_error())
+            } else {
+                Ok(Vec::new())
+            };
+        }
+
+        let mut parsed = Vec::with_capacity(values.len());
+        let mut errors = Vec::new();
+        for (i, value) in values.into_iter().enumerate() {
+            match value.parse::<T>() {
+                Ok(v) => parsed.push(v),
+                Err(e) => errors.push(format!("element {}: {}", i, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(parsed)
+        } else {
+            anyhow::bail!("Couldn't parse flag '{}':\n{}", self.name, errors.join("\n"))
+        }
+    }
+}
+
+/// Declared shape of a single flag, independent of the `T` it eventually
+/// parses to. `CommandSpec` keeps these type-erased so a heterogeneous set of
+/// flags can live in one schema and drive both validation and `--help` text.
+#[derive(Debug, Clone)]
+pub struct FlagSpec {
+    name: String,
+    alias: Option<String>,
+    type_name: String,
+    default: Option<String>,
+    help: Option<String>,
+    required: bool,
+}
+
+impl FlagSpec {
+    pub fn new(name: &str, type_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            alias: None,
+            type_name: type_name.to_string(),
+            default: None,
+            help: None,
+            required: false,
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    pub fn default(mut self, default: &str) -> Self {
+        self.default = Some(default.to_string());
+        self
+    }
+
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+}
+
+/// Declared shape of a boolean switch.
+#[derive(Debug, Clone)]
+pub struct SwitchSpec {
+    name: String,
+    alias: Option<String>,
+    help: Option<String>,
+}
+
+impl SwitchSpec {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            alias: None,
+            help: None,
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+}
+
+/// Declared shape of a positional argument.
+#[derive(Debug, Clone)]
+pub struct PositionalSpec {
+    name: String,
+    type_name: String,
+    help: Option<String>,
+    required: bool,
+}
+
+impl PositionalSpec {
+    pub fn new(name: &str, type_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            help: None,
+            required: true,
+        }
+    }
+
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+}
+
+/// A reusable, declarative description of a command's flags, switches and
+/// positional arguments. Builds once, then drives parsing validation, usage
+/// text and `--help` output so they can't drift apart like hand-written
+/// `Flag::new(...)` calls scattered across a file would.
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    name: String,
+    flags: Vec<FlagSpec>,
+    switches: Vec<SwitchSpec>,
+    positionals: Vec<PositionalSpec>,
+    subcommands: Vec<String>,
+    conflict_groups: Vec<Vec<String>>,
+    requirements: Vec<(String, String)>,
+}
+
+impl CommandSpec {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            flags: Vec::new(),
+            switches: Vec::new(),
+            positionals: Vec::new(),
+            subcommands: Vec::new(),
+            conflict_groups: Vec::new(),
+            requirements: Vec::new(),
+        }
+    }
+
+    pub fn flag(mut self, flag: FlagSpec) -> Self {
+        self.flags.push(flag);
+        self
+    }
+
+    pub fn subcommand(mut self, name: &str) -> Self {
+        self.subcommands.push(name.to_string());
+        self
+    }
+
+    /// Declares that at most one of `names` may be present on a parsed
+    /// `Command` at once (checked against both switches and flags).
+    pub fn conflicts(mut self, names: &[&str]) -> Self {
+        self.conflict_groups
+            .push(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// Declares that if `name` is present, `required` must be present too.
+    pub fn requires(mut self, name: &str, required: &str) -> Self {
+        self.requirements
+            .push((name.to_string(), required.to_string()));
+        self
+    }
+
+    pub fn switch(mut self, switch: SwitchSpec) -> Self {
+        self.switches.push(switch);
+        self
+    }
+
+    pub fn positional(mut self, positional: PositionalSpec) -> Self {
+        self.positionals.push(positional);
+        self
+    }
+
+    pub fn usage(&self) -> String {
+        let mut parts = vec![self.name.clone()];
+
+        for flag in &self.flags {
+            let flag_usage = format!("--{} <{}>", flag.name, flag.type_name);
+            parts.push(if flag.required {
+                flag_usage
+            } else {
+                format!("[{}]", flag_usage)
+            });
+        }
+
+        for switch in &self.switches {
+            parts.push(format!("[--{}]", switch.name));
+        }
+
+        for positional in &self.positionals {
+            parts.push(if positional.required {
+                format!("<{}>", positional.name)
+            } else {
+                format!("[<{}>]", positional.name)
+            });
+        }
+
+        parts.join(" ")
+    }
+
+    pub fn help(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "USAGE:").unwrap();
+        writeln!(out, "  {}", self.usage()).unwrap();
+
+        if !self.flags.is_empty() {
+            writeln!(out, "\nFLAGS:").unwrap();
+            for flag in &self.flags {
+                write!(out, "  --{}", flag.name).unwrap();
+                if let Some(alias) = &flag.alias {
+                    write!(out, ", -{}", alias).unwrap();
+                }
+                write!(out, " <{}>", flag.type_name).unwrap();
+                if let Some(help) = &flag.help {
+                    write!(out, "  {}", help).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+        }
+
+        if !self.switches.is_empty() {
+            writeln!(out, "\nSWITCHES:").unwrap();
+            for switch in &self.switches {
+                write!(out, "  --{}", switch.name).unwrap();
+                if let Some(alias) = &switch.alias {
+                    write!(out, ", -{}", alias).unwrap();
+                }
+                if let Some(help) = &switch.help {
+                    write!(out, "  {}", help).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+        }
+
+        if !self.positionals.is_empty() {
+            writeln!(out, "\nARGS:").unwrap();
+            for positional in &self.positionals {
+                write!(out, "  <{}> <{}>", positional.name, positional.type_name).unwrap();
+                if let Some(help) = &positional.help {
+                    write!(out, "  {}", help).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+        }
+
+        out
+    }
+
+    /// Validates an already-parsed `Command` against this schema, collecting
+    /// every missing required flag/argument into a single aggregated error
+    /// instead of bailing on the first one found.
+    ///
+    /// Takes `cmd` by `&mut` rather than `&` to match `Command`'s other
+    /// schema-driven entry points, even though nothing is drained here today.
+    pub fn parse(&self, cmd: &mut Command) -> Result<()> {
+        let mut errors = Vec::new();
+
+        // Declared positional `i` corresponds to `cmd.arguments[i]` (the same
+        // correspondence `Command::get_next_argument` relies on), so a
+        // missing required positional is checked per-slot rather than by
+        // comparing raw counts: an optional positional declared before a
+        // required one still "fills" a slot, it just doesn't need to.
+        for (i, positional) in self.positionals.iter().enumerate() {
+            if positional.required && cmd.arguments.len() <= i {
+                errors.push(format!("missing required argument '<{}>'", positional.name));
+            }
         }
+
+        for flag in &self.flags {
+            if !flag.required || flag.default.is_some() {
+                continue;
+            }
+
+            let present = cmd.flags.contains_key(&flag.name)
+                || flag
+                    .alias
+                    .as_ref()
+                    .is_some_and(|alias| cmd.flags.contains_key(alias));
+
+            if !present {
+                errors.push(missing_flag_message(&flag.name, flag.alias.as_deref()));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!(errors.join("\n"))
+        }
+    }
+
+    /// Checks `conflicts`/`requires` relationships against an already-parsed
+    /// `Command`, returning a single aggregated error naming every violated
+    /// rule instead of hand-written `if check_switch(...) && ...` guards.
+    ///
+    /// `conflicts`/`requires` groups are declared by a flag/switch's
+    /// canonical (long) name, but `cmd` only knows about whatever literal
+    /// text the user typed, which may be the alias instead. So before
+    /// checking groups, each declared switch/flag is resolved back to its
+    /// canonical name if either its name *or* its alias shows up on `cmd`.
+    pub fn validate(&self, cmd: &Command) -> Result<()> {
+        let present: HashSet<&str> = self
+            .switches
+            .iter()
+            .filter(|switch| {
+                cmd.switches.contains(&switch.name)
+                    || switch
+                        .alias
+                        .as_ref()
+                        .is_some_and(|alias| cmd.switches.contains(alias))
+            })
+            .map(|switch| switch.name.as_str())
+            .chain(self.flags.iter().filter_map(|flag| {
+                let present = cmd.flags.contains_key(&flag.name)
+                    || flag
+                        .alias
+                        .as_ref()
+                        .is_some_and(|alias| cmd.flags.contains_key(alias));
+                present.then_some(flag.name.as_str())
+            }))
+            .collect();
+
+        let mut errors = Vec::new();
+
+        for group in &self.conflict_groups {
+            let present_in_group: Vec<&str> = group
+                .iter()
+                .map(String::as_str)
+                .filter(|name| present.contains(name))
+                .collect();
+
+            if present_in_group.len() > 1 {
+                errors.push(format!(
+                    "'{}' cannot be used together",
+                    present_in_group.join("', '")
+                ));
+            }
+        }
+
+        for (name, required) in &self.requirements {
+            if present.contains(name.as_str()) && !present.contains(required.as_str()) {
+                errors.push(format!("'{}' requires '{}'", name, required));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!(errors.join("\n"))
+        }
+    }
+}
+
+/// Escapes single quotes in `text` for interpolation into a single-quoted
+/// POSIX-shell-style string literal, via the standard `'\''` substitution
+/// (close the quote, an escaped literal quote, reopen the quote). Used by
+/// the zsh and fish completion generators so help text containing an
+/// apostrophe (e.g. "don't break") doesn't break out of its `'...'` literal.
+fn escape_single_quotes(text: &str) -> String {
+    text.replace('\'', "'\\''")
+}
+
+/// Shells `CommandSpec::generate_completion` knows how to target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl CommandSpec {
+    /// Renders a completion script for `shell`, pulling flag/switch/
+    /// subcommand names and help text straight from the schema so the
+    /// completions can't drift out of sync with what `parse` accepts.
+    pub fn generate_completion(&self, shell: Shell, bin_name: &str) -> String {
+        match shell {
+            Shell::Bash => self.bash_completion(bin_name),
+            Shell::Zsh => self.zsh_completion(bin_name),
+            Shell::Fish => self.fish_completion(bin_name),
+        }
+    }
+
+    fn bash_completion(&self, bin_name: &str) -> String {
+        let long_flags = self
+            .flags
+            .iter()
+            .map(|f| format!("--{}", f.name))
+            .chain(self.switches.iter().map(|s| format!("--{}", s.name)));
+        let short_flags = self
+            .flags
+            .iter()
+            .filter_map(|f| f.alias.as_deref())
+            .chain(self.switches.iter().filter_map(|s| s.alias.as_deref()))
+            .map(|alias| format!("-{}", alias));
+        let words: Vec<String> = long_flags.chain(short_flags).collect();
+        let subcommands = self.subcommands.join(" ");
+
+        let mut out = String::new();
+        writeln!(out, "_{}_completions() {{", bin_name).unwrap();
+        writeln!(out, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"").unwrap();
+        writeln!(out, "    if [[ \"$cur\" == -* ]]; then").unwrap();
+        writeln!(
+            out,
+            "        COMPREPLY=( $(compgen -W \"{}\" -- \"$cur\") )",
+            words.join(" ")
+        )
+        .unwrap();
+        writeln!(out, "    else").unwrap();
+        writeln!(
+            out,
+            "        COMPREPLY=( $(compgen -W \"{}\" -- \"$cur\") )",
+            subcommands
+        )
+        .unwrap();
+        writeln!(out, "    fi").unwrap();
+        writeln!(out, "}}").unwrap();
+        writeln!(out, "complete -F _{}_completions {}", bin_name, bin_name).unwrap();
+        out
+    }
+
+    fn zsh_completion(&self, bin_name: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "#compdef {}", bin_name).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "_{}() {{", bin_name).unwrap();
+        writeln!(out, "    _arguments \\").unwrap();
+
+        for flag in &self.flags {
+            let alias = flag
+                .alias
+                .as_ref()
+                .map(|a| format!("-{} ", a))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "        '{}--{}[{}]:{}' \\",
+                alias,
+                flag.name,
+                escape_single_quotes(flag.help.as_deref().unwrap_or_default()),
+                flag.name
+            )
+            .unwrap();
+        }
+
+        for switch in &self.switches {
+            let alias = switch
+                .alias
+                .as_ref()
+                .map(|a| format!("-{} ", a))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "        '{}--{}[{}]' \\",
+                alias,
+                switch.name,
+                escape_single_quotes(switch.help.as_deref().unwrap_or_default())
+            )
+            .unwrap();
+        }
+
+        if !self.subcommands.is_empty() {
+            writeln!(out, "        '1:subcommand:({})' \\", self.subcommands.join(" ")).unwrap();
+        }
+
+        writeln!(out, "}}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "_{} \"$@\"", bin_name).unwrap();
+        out
+    }
+
+    fn fish_completion(&self, bin_name: &str) -> String {
+        let mut out = String::new();
+
+        for flag in &self.flags {
+            write!(out, "complete -c {} -l {}", bin_name, flag.name).unwrap();
+            if let Some(alias) = &flag.alias {
+                write!(out, " -s {}", alias).unwrap();
+            }
+            if let Some(help) = &flag.help {
+                write!(out, " -d '{}'", escape_single_quotes(help)).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        for switch in &self.switches {
+            write!(out, "complete -c {} -l {}", bin_name, switch.name).unwrap();
+            if let Some(alias) = &switch.alias {
+                write!(out, " -s {}", alias).unwrap();
+            }
+            if let Some(help) = &switch.help {
+                write!(out, " -d '{}'", escape_single_quotes(help)).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        for subcommand in &self.subcommands {
+            writeln!(
+                out,
+                "complete -c {} -n '__fish_use_subcommand' -a {}",
+                bin_name, subcommand
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_long_flag_equals_value() {
+        let cmd = Command::parse("--k=v".to_string()).unwrap();
+        assert_eq!(cmd.flags.get("k").unwrap(), &VecDeque::from(["v".to_string()]));
+    }
+
+    #[test]
+    fn inline_long_flag_equals_quoted_value() {
+        let cmd = Command::parse("--k=\"a b\"".to_string()).unwrap();
+        assert_eq!(
+            cmd.flags.get("k").unwrap(),
+            &VecDeque::from(["a b".to_string()])
+        );
+    }
+
+    #[test]
+    fn short_cluster_equals_value() {
+        // "-abc=1": "a" and "b" stay switches, "c" takes the value.
+        let cmd = Command::parse("-abc=1".to_string()).unwrap();
+        assert_eq!(cmd.switches, HashSet::from(["a".to_string(), "b".to_string()]));
+        assert_eq!(cmd.flags.get("c").unwrap(), &VecDeque::from(["1".to_string()]));
+    }
+
+    #[test]
+    fn short_flag_inline_digit_value() {
+        // "-i12": no "=", but a digit directly follows the flag letter.
+        let cmd = Command::parse("-i12".to_string()).unwrap();
+        assert!(cmd.switches.is_empty());
+        assert_eq!(cmd.flags.get("i").unwrap(), &VecDeque::from(["12".to_string()]));
+    }
+
+    #[test]
+    fn inline_long_flag_equals_empty_value() {
+        // "--k=" with nothing after the "=" is a flag with an empty value,
+        // not a crash.
+        let cmd = Command::parse("--k=".to_string()).unwrap();
+        assert_eq!(cmd.flags.get("k").unwrap(), &VecDeque::from(["".to_string()]));
+    }
+
+    #[test]
+    fn inline_long_flag_equals_empty_quoted_value() {
+        let cmd = Command::parse("--k=\"\"".to_string()).unwrap();
+        assert_eq!(cmd.flags.get("k").unwrap(), &VecDeque::from(["".to_string()]));
+    }
+
+    #[test]
+    fn short_cluster_equals_empty_value() {
+        let cmd = Command::parse("-abc=".to_string()).unwrap();
+        assert_eq!(cmd.switches, HashSet::from(["a".to_string(), "b".to_string()]));
+        assert_eq!(cmd.flags.get("c").unwrap(), &VecDeque::from(["".to_string()]));
+    }
+
+    #[test]
+    fn zsh_completion_escapes_single_quotes_in_help() {
+        let spec = CommandSpec::new("foo").flag(
+            FlagSpec::new("name", "string").help("don't break"),
+        );
+        let script = spec.generate_completion(Shell::Zsh, "foo");
+        assert!(script.contains("don'\\''t break"));
+        assert!(!script.contains("[don't break]"));
+    }
+
+    #[test]
+    fn fish_completion_escapes_single_quotes_in_help() {
+        let spec = CommandSpec::new("foo").switch(
+            SwitchSpec::new("verbose").help("don't break"),
+        );
+        let script = spec.generate_completion(Shell::Fish, "foo");
+        assert!(script.contains("-d 'don'\\''t break'"));
+    }
+
+    #[test]
+    fn subcommand_splits_verb_and_child_tokens() {
+        // "--verbose=true" uses the inline-value form, so it's unambiguously
+        // resolved before "commit" is ever considered as its value (see the
+        // ambiguity documented on `Command::parse`).
+        let mut cmd = Command::parse("--verbose=true commit -m msg".to_string()).unwrap();
+        let (verb, mut child) = cmd.subcommand().unwrap();
+
+        assert_eq!(verb, "commit");
+        assert_eq!(cmd.get_next_flag("verbose").unwrap(), "true");
+        assert_eq!(child.get_next_flag("m").unwrap(), "msg");
+    }
+
+    #[test]
+    fn subcommand_does_not_leak_child_flags_into_parent() {
+        let mut cmd = Command::parse("commit -m msg".to_string()).unwrap();
+        let (verb, _child) = cmd.subcommand().unwrap();
+
+        assert_eq!(verb, "commit");
+        assert!(cmd.flags.is_empty());
+        assert!(cmd.arguments.is_empty());
+    }
+
+    #[test]
+    fn subcommand_returns_none_without_a_verb() {
+        let mut cmd = Command::parse("--verbose".to_string()).unwrap();
+        assert!(cmd.subcommand().is_none());
+    }
+
+    #[test]
+    fn split_at_subcommand_keeps_parent_and_child_separate() {
+        let cmd = Command::parse("--verbose=true commit -m msg".to_string()).unwrap();
+        let (parent, suffix) = cmd.split_at_subcommand().unwrap();
+
+        assert_eq!(parent.flags.get("verbose").unwrap(), &VecDeque::from(["true".to_string()]));
+        assert_eq!(Command::parse(suffix).unwrap().flags.get("m").unwrap(), &VecDeque::from(["msg".to_string()]));
+    }
+
+    #[test]
+    fn dispatch_invokes_the_matching_route() {
+        fn commit(cmd: &mut Command) -> Result<()> {
+            cmd.get_next_flag("m").map(|_| ())
+        }
+
+        let mut cmd = Command::parse("commit -m msg".to_string()).unwrap();
+        let routes: &[Route] = &[("commit", commit)];
+        cmd.dispatch(routes).unwrap();
+    }
+
+    #[test]
+    fn dispatch_errors_on_unknown_verb() {
+        fn commit(_cmd: &mut Command) -> Result<()> {
+            Ok(())
+        }
+
+        let mut cmd = Command::parse("push".to_string()).unwrap();
+        let routes: &[Route] = &[("commit", commit)];
+        assert!(cmd.dispatch(routes).is_err());
+    }
+
+    fn conflicting_spec() -> CommandSpec {
+        CommandSpec::new("foo")
+            .switch(SwitchSpec::new("verbose").alias("v"))
+            .switch(SwitchSpec::new("quiet").alias("q"))
+            .conflicts(&["verbose", "quiet"])
+    }
+
+    #[test]
+    fn validate_rejects_conflicting_long_switches() {
+        let cmd = Command::parse("--verbose --quiet".to_string()).unwrap();
+        assert!(conflicting_spec().validate(&cmd).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_conflicting_switches_via_alias() {
+        // Both switches were typed by their short alias, not their
+        // canonical name, but the conflict is still on the canonical pair.
+        let cmd = Command::parse("-v -q".to_string()).unwrap();
+        assert!(conflicting_spec().validate(&cmd).is_err());
+    }
+
+    #[test]
+    fn validate_allows_only_one_of_a_conflict_group() {
+        let cmd = Command::parse("--verbose".to_string()).unwrap();
+        assert!(conflicting_spec().validate(&cmd).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_missing_requirement() {
+        let spec = CommandSpec::new("foo")
+            .flag(FlagSpec::new("output", "string"))
+            .flag(FlagSpec::new("format", "string").alias("f"))
+            .requires("output", "format");
+
+        let cmd = Command::parse("--output out.txt".to_string()).unwrap();
+        assert!(spec.validate(&cmd).is_err());
+    }
+
+    #[test]
+    fn validate_accepts_requirement_satisfied_via_alias() {
+        let spec = CommandSpec::new("foo")
+            .flag(FlagSpec::new("output", "string"))
+            .flag(FlagSpec::new("format", "string").alias("f"))
+            .requires("output", "format");
+
+        let cmd = Command::parse("--output out.txt -f json".to_string()).unwrap();
+        assert!(spec.validate(&cmd).is_ok());
+    }
+
+    #[test]
+    fn required_flag_errors_with_dedicated_message_when_absent() {
+        let mut cmd = Command::parse("".to_string()).unwrap();
+        let err = Flag::<i32>::new("count").required().parse(&mut cmd).unwrap_err();
+        assert!(err.to_string().contains("required flag '--count' was not provided"));
+    }
+
+    #[test]
+    fn required_flag_with_alias_names_both_forms_in_error() {
+        let mut cmd = Command::parse("".to_string()).unwrap();
+        let err = Flag::<i32>::new("count")
+            .alias("c")
+            .required()
+            .parse(&mut cmd)
+            .unwrap_err();
+        assert!(err.to_string().contains("'--count' ('-c')"));
+    }
+
+    #[test]
+    fn required_flag_parses_when_present() {
+        let mut cmd = Command::parse("--count 3".to_string()).unwrap();
+        let value = Flag::<i32>::new("count").required().parse(&mut cmd).unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    fn repeated_flag_collects_every_occurrence() {
+        let mut cmd = Command::parse("-i 1 -i 2 -i 3".to_string()).unwrap();
+        let values = Flag::<i32>::new("i").repeated().parse_all(&mut cmd).unwrap();
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn repeated_flag_merges_name_and_alias_occurrences() {
+        let mut cmd = Command::parse("--file a --file b -f c".to_string()).unwrap();
+        let values = Flag::<String>::new("file")
+            .alias("f")
+            .repeated()
+            .parse_all(&mut cmd)
+            .unwrap();
+        assert_eq!(values, vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn repeated_flag_required_errors_when_absent() {
+        let mut cmd = Command::parse("".to_string()).unwrap();
+        let err = Flag::<i32>::new("i")
+            .required()
+            .repeated()
+            .parse_all(&mut cmd)
+            .unwrap_err();
+        assert!(err.to_string().contains("required flag '--i' was not provided"));
+    }
+
+    #[test]
+    fn repeated_flag_optional_returns_empty_when_absent() {
+        let mut cmd = Command::parse("".to_string()).unwrap();
+        let values = Flag::<i32>::new("i").repeated().parse_all(&mut cmd).unwrap();
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn repeated_flag_aggregates_parse_errors_with_indices() {
+        let mut cmd = Command::parse("-i 1 -i notanumber".to_string()).unwrap();
+        let err = Flag::<i32>::new("i").repeated().parse_all(&mut cmd).unwrap_err();
+        assert!(err.to_string().contains("element 1"));
     }
 }